@@ -1,4 +1,4 @@
-use acid_list::{AcidList, LinkIndex};
+use acid_list::{AcidList, LinkIndex, MmapBacking};
 use std::io;
 
 fn main() -> io::Result<()> {
@@ -14,14 +14,15 @@ fn main() -> io::Result<()> {
     }
 
     let file = std::fs::OpenOptions::new().read(true).write(true).open(path)?;
-    let mut list = AcidList::<[u8; 32]>::open(file)?;
+    let mut list = AcidList::<[u8; 32], MmapBacking>::open_file(file)?;
 
     let mut prev = LinkIndex::Head(0);
 
     for idx in access.into_iter() {
-        list.move_before(idx, prev);
+        list.move_before(idx, prev)?;
         prev = LinkIndex::Node(idx);
     }
 
-    list.close()
+    list.close();
+    Ok(())
 }
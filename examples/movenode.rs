@@ -1,4 +1,4 @@
-use acid_list::{AcidList, LinkIndex};
+use acid_list::{AcidList, LinkIndex, MmapBacking};
 use std::io;
 
 fn main() -> io::Result<()> {
@@ -32,9 +32,10 @@ fn main() -> io::Result<()> {
         .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
 
     let file = std::fs::OpenOptions::new().read(true).write(true).open(path)?;
-    let mut list = AcidList::<[u8; 32]>::open(file)?;
+    let mut list = AcidList::<[u8; 32], MmapBacking>::open_file(file)?;
 
-    direction(&mut list, from_idx, kind(to_idx));
+    direction(&mut list, from_idx, kind(to_idx))?;
 
-    list.close()
+    list.close();
+    Ok(())
 }
@@ -17,5 +17,6 @@ fn main() -> io::Result<()> {
     })?;
 
     let header = Header::<[u8; 32]>::new(heads, nodes);
-    AcidList::create(path, header)?.close()
+    AcidList::create_file(path, header)?.close();
+    Ok(())
 }
@@ -0,0 +1,102 @@
+//! Turns the fixed arena [`AcidList::create`] preallocates into a
+//! growable one: a hidden free list (one head past the ones the
+//! caller declared) holds nodes that aren't part of any list yet, so
+//! they can be handed out with [`alloc`](AcidList::alloc) and taken
+//! back with [`free`](AcidList::free), and [`grow`](AcidList::grow)
+//! can add more of them without disturbing any existing node's index.
+
+use crate::{AcidList, Backing, Growable, Link, LinkIndex, ListHeader, Node, NodeIndex};
+use core::mem;
+
+impl<T, B: Backing, H: ListHeader<T>> AcidList<T, B, H> {
+    fn free_list_head(&self) -> NodeIndex {
+        self.header().heads()
+    }
+
+    /// Take a node off the free list, if one is available. The node
+    /// comes back detached from every list; splice it in with
+    /// [`move_before`](AcidList::move_before) or
+    /// [`move_after`](AcidList::move_after).
+    ///
+    /// The free list starts out empty: [`AcidList::create`] links every
+    /// node it preallocates into list 0 instead, so a freshly created
+    /// list's `alloc` returns `None` until the caller
+    /// [`free`](AcidList::free)s some of those nodes or
+    /// [`grow`](AcidList::grow)s the list for more.
+    pub fn alloc(&mut self) -> Option<NodeIndex> {
+        let free_list = LinkIndex::Head(self.free_list_head());
+        let idx = match self.neighbors(free_list).next {
+            LinkIndex::Node(idx) => idx,
+            LinkIndex::Head(_) => return None,
+        };
+
+        self.unlink(idx).ok()?;
+        Some(idx)
+    }
+
+    /// Remove `idx` from whatever list it's currently in and push it
+    /// onto the free list, for a future [`alloc`](AcidList::alloc) to
+    /// hand back out.
+    pub fn free(&mut self, idx: NodeIndex) -> Result<(), B::Error> {
+        self.unlink(idx)?;
+        let free_list_head = self.free_list_head();
+        self.move_after(idx, LinkIndex::Head(free_list_head))
+    }
+
+    /// Detach `idx` from whatever list (or free list) it's in, leaving
+    /// it as a singleton pointing to itself.
+    fn unlink(&mut self, idx: NodeIndex) -> Result<(), B::Error> {
+        let from = self.link(LinkIndex::Node(idx));
+        if from.previous == idx && from.next == idx {
+            // already detached
+            return Ok(());
+        }
+
+        self.move_to(idx, from, Link {
+            previous: idx,
+            next: idx,
+        })
+    }
+}
+
+impl<T, B: Growable, H: ListHeader<T>> AcidList<T, B, H> {
+    /// Reserve `additional` more nodes past the end of the backing
+    /// storage and thread them onto the free list. Every existing
+    /// node's index, and everything already linked with
+    /// [`move_before`](AcidList::move_before)/[`move_after`](AcidList::move_after),
+    /// is unaffected.
+    pub fn grow(&mut self, additional: NodeIndex) -> Result<(), B::Error> {
+        if additional == 0 {
+            return Ok(());
+        }
+
+        let old_nodes = self.header().nodes();
+        let new_nodes = old_nodes + additional;
+        let new_file_size =
+            self.header().nodes_offset() + new_nodes as u64 * mem::size_of::<Node<T>>() as u64;
+
+        self.backing.resize(new_file_size as usize)?;
+        self.header_mut().set_nodes(new_nodes);
+
+        // chain the newly available nodes together, the same way
+        // create() chains the nodes it preallocates
+        for idx in old_nodes..new_nodes {
+            *self.link_mut(idx) = Link {
+                previous: idx.wrapping_sub(1),
+                next: idx.wrapping_add(1),
+            };
+        }
+
+        // splice that chain onto the front of the free list
+        let free_list_head = self.free_list_head();
+        let free_list_node = LinkIndex::Head(free_list_head).to_node();
+        let old_front = self.link(LinkIndex::Head(free_list_head)).next;
+
+        self.link_mut(old_nodes).previous = free_list_node;
+        self.link_mut(new_nodes - 1).next = old_front;
+        self.link_mut(old_front).previous = new_nodes - 1;
+        self.head_mut(free_list_head).next = old_nodes;
+
+        Ok(())
+    }
+}
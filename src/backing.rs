@@ -0,0 +1,45 @@
+//! Storage abstraction so [`AcidList`](crate::AcidList) isn't tied to
+//! any particular memory source.
+
+/// The raw byte storage an [`AcidList`](crate::AcidList) operates on.
+///
+/// An implementation just needs to hand back a stable pointer and
+/// length covering the list's header, heads, and nodes, and know how
+/// to make writes through that pointer durable. [`MmapBacking`](crate::MmapBacking)
+/// and [`SliceBacking`](crate::SliceBacking) are the two implementations
+/// this crate provides.
+pub trait Backing {
+    /// The error [`sync`](Backing::sync) can fail with.
+    type Error;
+
+    /// A pointer to the first byte of the backing storage. Must stay
+    /// valid, and must keep pointing at the same bytes, for as long as
+    /// this value exists, except that [`Growable::resize`] may move it.
+    fn base(&self) -> *mut u8;
+
+    /// The number of bytes available starting at [`base`](Backing::base).
+    fn len(&self) -> usize;
+
+    /// Whether [`len`](Backing::len) is zero.
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Make sure every write made through [`base`](Backing::base) so
+    /// far is durable before this call returns.
+    fn sync(&self) -> Result<(), Self::Error>;
+}
+
+/// A [`Backing`] that can be resized after it's created, so
+/// [`AcidList::grow`](crate::AcidList::grow) has somewhere to put the
+/// nodes it adds. Not every backing can do this: [`SliceBacking`](crate::SliceBacking)
+/// wraps memory it doesn't own the size of, so it only implements
+/// [`Backing`]; [`MmapBacking`](crate::MmapBacking) owns a whole file
+/// and can grow it.
+pub trait Growable: Backing {
+    /// Resize the backing storage to `new_len` bytes and update
+    /// [`base`](Backing::base)/[`len`](Backing::len) to match, moving
+    /// the mapping if necessary. `new_len` is always larger than the
+    /// current [`len`](Backing::len).
+    fn resize(&mut self, new_len: usize) -> Result<(), Self::Error>;
+}
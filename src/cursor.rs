@@ -0,0 +1,120 @@
+//! Safe traversal over an [`AcidList`], as an alternative to manually
+//! calling [`AcidList::neighbors`] and matching on [`LinkIndex`].
+
+use crate::{AcidList, Backing, Header, LinkIndex, ListHeader, NodeIndex};
+
+/// A read-only position within a list, reached from a particular head.
+pub struct Cursor<'a, T, B: Backing, H: ListHeader<T> = Header<T>> {
+    list: &'a AcidList<T, B, H>,
+    current: LinkIndex,
+}
+
+impl<'a, T, B: Backing, H: ListHeader<T>> Cursor<'a, T, B, H> {
+    /// Start a cursor positioned at `head_idx`, before the first node.
+    pub fn head(list: &'a AcidList<T, B, H>, head_idx: NodeIndex) -> Self {
+        Cursor {
+            list,
+            current: LinkIndex::Head(head_idx),
+        }
+    }
+
+    /// The contents of the node the cursor is on, or `None` if the
+    /// cursor is on the head itself.
+    pub fn current(&self) -> Option<&T> {
+        match self.current {
+            LinkIndex::Node(idx) => Some(self.list.get(idx)),
+            LinkIndex::Head(_) => None,
+        }
+    }
+
+    /// Advance to the next node, wrapping around to the head.
+    pub fn move_next(&mut self) {
+        self.current = self.list.neighbors(self.current).next;
+    }
+
+    /// Move to the previous node, wrapping around to the head.
+    pub fn move_prev(&mut self) {
+        self.current = self.list.neighbors(self.current).previous;
+    }
+}
+
+/// Like [`Cursor`], but can also reorder the list as it goes.
+pub struct CursorMut<'a, T, B: Backing, H: ListHeader<T> = Header<T>> {
+    list: &'a mut AcidList<T, B, H>,
+    current: LinkIndex,
+}
+
+impl<'a, T, B: Backing, H: ListHeader<T>> CursorMut<'a, T, B, H> {
+    /// Start a cursor positioned at `head_idx`, before the first node.
+    pub fn head(list: &'a mut AcidList<T, B, H>, head_idx: NodeIndex) -> Self {
+        CursorMut {
+            list,
+            current: LinkIndex::Head(head_idx),
+        }
+    }
+
+    /// The contents of the node the cursor is on, or `None` if the
+    /// cursor is on the head itself.
+    pub fn current(&self) -> Option<&T> {
+        match self.current {
+            LinkIndex::Node(idx) => Some(self.list.get(idx)),
+            LinkIndex::Head(_) => None,
+        }
+    }
+
+    /// Advance to the next node, wrapping around to the head.
+    pub fn move_next(&mut self) {
+        self.current = self.list.neighbors(self.current).next;
+    }
+
+    /// Move to the previous node, wrapping around to the head.
+    pub fn move_prev(&mut self) {
+        self.current = self.list.neighbors(self.current).previous;
+    }
+
+    /// Move `from_idx` to just before the cursor's current position,
+    /// leaving the cursor on the same node (or head) it started on.
+    pub fn splice_before(&mut self, from_idx: NodeIndex) -> Result<(), B::Error> {
+        self.list.move_before(from_idx, self.current)
+    }
+
+    /// Move `from_idx` to just after the cursor's current position,
+    /// leaving the cursor on the same node (or head) it started on.
+    pub fn splice_after(&mut self, from_idx: NodeIndex) -> Result<(), B::Error> {
+        self.list.move_after(from_idx, self.current)
+    }
+}
+
+/// Iterates the nodes reachable from `head_idx`, in list order, until
+/// traversal wraps back around to that head.
+pub struct ListIter<'a, T, B: Backing, H: ListHeader<T> = Header<T>> {
+    list: &'a AcidList<T, B, H>,
+    head: LinkIndex,
+    current: LinkIndex,
+}
+
+impl<'a, T, B: Backing, H: ListHeader<T>> ListIter<'a, T, B, H> {
+    pub(crate) fn new(list: &'a AcidList<T, B, H>, head_idx: NodeIndex) -> Self {
+        let head = LinkIndex::Head(head_idx);
+        ListIter {
+            list,
+            head,
+            current: head,
+        }
+    }
+}
+
+impl<'a, T, B: Backing, H: ListHeader<T>> Iterator for ListIter<'a, T, B, H> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        match self.list.neighbors(self.current).next {
+            next if next == self.head => None,
+            LinkIndex::Node(idx) => {
+                self.current = LinkIndex::Node(idx);
+                Some(self.list.get(idx))
+            }
+            LinkIndex::Head(_) => None,
+        }
+    }
+}
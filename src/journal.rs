@@ -0,0 +1,165 @@
+//! A small write-ahead journal that gives [`AcidList::move_before`] and
+//! [`AcidList::move_after`] crash atomicity. Without it, a crash (or a
+//! partial `sync`) between the handful of link writes a reorder makes
+//! can leave the list structurally corrupt; with it, a reorder either
+//! lands completely or is replayed to completion the next time the
+//! file is opened.
+
+use crate::{AcidList, Backing, Link, LinkIndex, ListHeader, NodeIndex};
+
+pub(crate) const JOURNAL_RECORDS: usize = 5;
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub(crate) struct JournalRecord {
+    node: NodeIndex,
+    previous: NodeIndex,
+    next: NodeIndex,
+}
+
+const EMPTY_RECORD: JournalRecord = JournalRecord {
+    node: 0,
+    previous: 0,
+    next: 0,
+};
+
+#[repr(C)]
+pub(crate) struct Journal {
+    pub(crate) commit: u32,
+    len: u32,
+    records: [JournalRecord; JOURNAL_RECORDS],
+}
+
+impl Journal {
+    pub(crate) fn empty() -> Self {
+        Journal {
+            commit: 0,
+            len: 0,
+            records: [EMPTY_RECORD; JOURNAL_RECORDS],
+        }
+    }
+}
+
+/// Accumulates the handful of link writes a single reorder makes,
+/// merging repeated writes to the same node so each touched node's
+/// link only ever needs to be journaled, and applied, once, at its
+/// final value.
+pub(crate) struct LinkPatch {
+    entries: [(NodeIndex, Link); JOURNAL_RECORDS],
+    len: usize,
+}
+
+impl LinkPatch {
+    pub(crate) fn new() -> Self {
+        LinkPatch {
+            entries: [(0, Link { previous: 0, next: 0 }); JOURNAL_RECORDS],
+            len: 0,
+        }
+    }
+
+    /// The link this patch currently holds for `idx`, reading it from
+    /// `list` the first time `idx` is touched.
+    pub(crate) fn entry<T, B: Backing, H: ListHeader<T>>(
+        &mut self,
+        list: &AcidList<T, B, H>,
+        idx: NodeIndex,
+    ) -> &mut Link {
+        if self.entries[..self.len].iter().all(|(i, _)| *i != idx) {
+            assert!(self.len < JOURNAL_RECORDS);
+            let link = list.link(LinkIndex::from_node(idx));
+            self.entries[self.len] = (idx, link);
+            self.len += 1;
+        }
+
+        let pos = self.entries[..self.len].iter().position(|(i, _)| *i == idx).unwrap();
+        &mut self.entries[pos].1
+    }
+
+    fn records(&self) -> ([JournalRecord; JOURNAL_RECORDS], usize) {
+        let mut records = [EMPTY_RECORD; JOURNAL_RECORDS];
+        for (record, (node, link)) in records.iter_mut().zip(&self.entries[..self.len]) {
+            *record = JournalRecord {
+                node: *node,
+                previous: link.previous,
+                next: link.next,
+            };
+        }
+        (records, self.len)
+    }
+}
+
+impl<T, B: Backing, H: ListHeader<T>> AcidList<T, B, H> {
+    pub(crate) unsafe fn journal_ptr(&self) -> *mut Journal {
+        let base = self.backing.base();
+        base.offset(self.header().journal_offset() as isize) as *mut Journal
+    }
+
+    pub(crate) fn journal(&self) -> &Journal {
+        unsafe { &*self.journal_ptr() }
+    }
+
+    pub(crate) fn journal_mut(&mut self) -> &mut Journal {
+        unsafe { &mut *self.journal_ptr() }
+    }
+
+    /// Apply `patch` with crash atomicity: stage it in the journal and
+    /// make that durable, apply it to the real links and make *that*
+    /// durable, then clear the journal so it isn't replayed again.
+    pub(crate) fn apply_patch(&mut self, patch: &LinkPatch) -> Result<(), B::Error> {
+        let (records, len) = patch.records();
+
+        let journal = self.journal_mut();
+        journal.len = len as u32;
+        journal.records[..len].copy_from_slice(&records[..len]);
+        self.backing.sync()?;
+
+        self.journal_mut().commit = 1;
+        self.backing.sync()?;
+
+        for record in &records[..len] {
+            *self.link_mut(record.node) = Link {
+                previous: record.previous,
+                next: record.next,
+            };
+        }
+        self.backing.sync()?;
+
+        self.journal_mut().commit = 0;
+        self.backing.sync()
+    }
+
+    /// Replay a journal left behind by a reorder that was interrupted
+    /// before it could clear the commit flag. Safe to call whether or
+    /// not a journal is actually pending, and safe to call more than
+    /// once, since every record holds an absolute link value rather
+    /// than a delta.
+    pub(crate) fn replay_journal(&mut self) {
+        if self.journal().commit == 0 {
+            return;
+        }
+
+        let journal = self.journal();
+        let len = journal.len as usize;
+        if len > JOURNAL_RECORDS {
+            // torn or garbage journal: there's nothing we can safely
+            // replay, so leave the links alone rather than indexing
+            // past the fixed-size records array
+            return;
+        }
+        let mut records = [EMPTY_RECORD; JOURNAL_RECORDS];
+        records[..len].copy_from_slice(&journal.records[..len]);
+
+        for record in &records[..len] {
+            *self.link_mut(record.node) = Link {
+                previous: record.previous,
+                next: record.next,
+            };
+        }
+
+        // best-effort: if this doesn't make it to stable storage, the
+        // journal is still valid and will simply be replayed again
+        let _ = self.backing.sync();
+        self.journal_mut().commit = 0;
+        let _ = self.backing.sync();
+    }
+}
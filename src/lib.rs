@@ -1,33 +1,62 @@
+#![cfg_attr(not(feature = "std"), no_std)]
 #![warn(nonstandard_style)]
 
+extern crate alloc;
+
+mod allocator;
+mod backing;
+mod cursor;
+mod journal;
+#[cfg(feature = "std")]
+mod mmap_backing;
+mod slice_backing;
+#[cfg(feature = "std")]
 mod syscall;
 
-use std::fmt;
+pub use backing::{Backing, Growable};
+pub use cursor::{Cursor, CursorMut, ListIter};
+#[cfg(feature = "std")]
+pub use mmap_backing::MmapBacking;
+pub use slice_backing::SliceBacking;
+
+use journal::{Journal, LinkPatch};
+
+use core::fmt;
+use core::marker::PhantomData;
+use core::mem;
+#[cfg(feature = "std")]
 use std::fs;
+#[cfg(feature = "std")]
 use std::io;
-use std::io::{Seek, Write};
-use std::marker::PhantomData;
-use std::mem;
-use std::os::unix::io::AsRawFd;
+#[cfg(feature = "std")]
+use std::path::Path;
 
 #[derive(Debug)]
-enum Error {
+pub enum Error<E> {
     NotInitialized,
-    WrongArchitecture,
     WrongDataType,
+    InvalidHeader(E),
 }
 
-impl fmt::Display for Error {
+impl<E: fmt::Display> fmt::Display for Error<E> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             Error::NotInitialized => write!(f, "can't open uninitialized backing file"),
-            Error::WrongArchitecture => write!(f, "can't open file from a different architecture"),
             Error::WrongDataType => write!(f, "can't open file with differently sized data"),
+            Error::InvalidHeader(e) => write!(f, "invalid header: {}", e),
         }
     }
 }
 
-impl std::error::Error for Error {}
+#[cfg(feature = "std")]
+impl<E: std::error::Error + 'static> std::error::Error for Error<E> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::InvalidHeader(e) => Some(e),
+            _ => None,
+        }
+    }
+}
 
 pub type NodeIndex = u32;
 const HEAD_FLAG: NodeIndex = 1 << 31;
@@ -57,12 +86,90 @@ impl LinkIndex {
     }
 }
 
-// XXX: Header could be a caller-provided repr(C) type implementing a
-// trait for the few things that AcidList actually needs from it,
-// including one method for validating the header on open and returning
-// a caller-meaningful error if that fails. Then applications could
-// store extra metadata for interpreting list contents, or schema
-// information to validate against the running version's schema.
+/// What [`AcidList::open`] needs from the first bytes of its backing
+/// storage. Applications implement this on their own `#[repr(C)]` type
+/// to embed whatever schema version, content codec, or magic number
+/// they need to tell their files apart from anyone else's, and to
+/// reject files that don't pass muster before `AcidList` ever touches
+/// the heads or nodes that follow.
+///
+/// [`Header`] is the built-in implementation, for callers who don't
+/// need anything beyond what `AcidList` itself requires.
+pub trait ListHeader<T>: Sized {
+    /// The error `validate` can fail with.
+    type Error;
+
+    /// How many list heads follow this header.
+    fn heads(&self) -> NodeIndex;
+
+    /// How many nodes are preallocated after the heads.
+    fn nodes(&self) -> NodeIndex;
+
+    /// Record a new node count after [`AcidList::grow`] has made room
+    /// for more nodes. Implementors should store `nodes` the same way
+    /// [`nodes`](ListHeader::nodes) reads it back.
+    fn set_nodes(&mut self, nodes: NodeIndex);
+
+    /// The element size this header was written for, so `AcidList`
+    /// can refuse to reinterpret a file's nodes as the wrong type.
+    fn data_size(&self) -> u32;
+
+    /// Check that this header describes a file `AcidList` can open:
+    /// right magic number, right schema version, whatever the
+    /// application needs. Called once, right after the header is read.
+    fn validate(&self) -> Result<(), Self::Error>;
+
+    /// The number of bytes this header itself occupies, so `AcidList`
+    /// knows where the heads array starts. Callers who add fields
+    /// beyond what `Header<T>` has don't need to override this;
+    /// `size_of_val(self)` already accounts for them.
+    fn layout(&self) -> u64 {
+        mem::size_of_val(self) as u64
+    }
+
+    /// Where the crash-atomicity journal starts, right after the
+    /// header. Every `AcidList` reserves space for one, regardless of
+    /// which `ListHeader` it's paired with.
+    fn journal_offset(&self) -> u64 {
+        align_to::<Journal>(self.layout())
+    }
+
+    fn heads_offset(&self) -> u64 {
+        align_to::<Link>(self.journal_offset() + mem::size_of::<Journal>() as u64)
+    }
+
+    /// One head beyond [`heads`](ListHeader::heads) is reserved for
+    /// [`AcidList`]'s free list, invisible to callers, so the heads
+    /// array is always one element longer than `heads()` says.
+    fn nodes_offset(&self) -> u64 {
+        align_to::<Node<T>>(
+            self.heads_offset() + (self.heads() as u64 + 1) * mem::size_of::<Link>() as u64,
+        )
+    }
+
+    fn file_size(&self) -> u64 {
+        self.nodes_offset() + self.nodes() as u64 * mem::size_of::<Node<T>>() as u64
+    }
+}
+
+#[derive(Debug)]
+pub enum HeaderError {
+    WrongArchitecture,
+}
+
+impl fmt::Display for HeaderError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HeaderError::WrongArchitecture => {
+                write!(f, "can't open file from a different architecture")
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for HeaderError {}
+
 #[repr(C)]
 pub struct Header<T> {
     magic: u32,
@@ -89,17 +196,33 @@ impl<T> Header<T> {
             marker: PhantomData,
         }
     }
+}
 
-    fn heads_offset(&self) -> u64 {
-        align_to::<Link>(mem::size_of_val(self) as u64)
+impl<T> ListHeader<T> for Header<T> {
+    type Error = HeaderError;
+
+    fn heads(&self) -> NodeIndex {
+        self.heads
     }
 
-    fn nodes_offset(&self) -> u64 {
-        align_to::<Node<T>>(self.heads_offset() + self.heads as u64 * mem::size_of::<Link>() as u64)
+    fn nodes(&self) -> NodeIndex {
+        self.nodes
     }
 
-    fn file_size(&self) -> u64 {
-        self.nodes_offset() + self.nodes as u64 * mem::size_of::<Node<T>>() as u64
+    fn set_nodes(&mut self, nodes: NodeIndex) {
+        self.nodes = nodes;
+    }
+
+    fn data_size(&self) -> u32 {
+        self.data_size
+    }
+
+    fn validate(&self) -> Result<(), HeaderError> {
+        if self.magic == HEADER_MAGIC {
+            Ok(())
+        } else {
+            Err(HeaderError::WrongArchitecture)
+        }
     }
 }
 
@@ -126,35 +249,46 @@ struct Node<T> {
     contents: T,
 }
 
-pub struct AcidList<T> {
-    base: *mut libc::c_void,
-    len: libc::size_t,
-    marker: PhantomData<Node<T>>,
+pub struct AcidList<T, B: Backing, H: ListHeader<T> = Header<T>> {
+    backing: B,
+    marker: PhantomData<(Node<T>, H)>,
 }
 
-impl<T> AcidList<T> {
-    pub fn create<P>(path: P, header: Header<T>) -> io::Result<Self>
-    where
-        P: AsRef<std::path::Path>,
-    {
-        let mut file = fs::OpenOptions::new()
-            .create_new(true)
-            .read(true)
-            .write(true)
-            .open(path)?;
-        file.set_len(header.file_size())?;
-        file.write_all(unsafe {
-            std::slice::from_raw_parts(
-                &header as *const Header<T> as *const u8,
+impl<T, B: Backing, H: ListHeader<T>> AcidList<T, B, H> {
+    /// Initialize `backing` as a fresh, empty list: every head declared
+    /// by `header` starts out empty, and the nodes `header` preallocates
+    /// all start out linked into list 0, *not* the free list, so
+    /// [`alloc`](AcidList::alloc) has nothing to hand out until the
+    /// caller [`free`](AcidList::free)s some of them or
+    /// [`grow`](AcidList::grow)s the list for more.
+    pub fn create(backing: B, header: H) -> Result<Self, Error<H::Error>> {
+        header.validate().map_err(Error::InvalidHeader)?;
+        assert_eq!(backing.len() as u64, header.file_size());
+
+        unsafe {
+            core::ptr::copy_nonoverlapping(
+                &header as *const H as *const u8,
+                backing.base(),
                 mem::size_of_val(&header),
-            )
-        })?;
+            );
+
+            // start with no journal pending, so open() below won't try to
+            // replay whatever was already in this memory before create()
+            // got a chance to initialize it
+            let journal_ptr = backing.base().offset(header.journal_offset() as isize) as *mut Journal;
+            *journal_ptr = Journal::empty();
+        }
 
         // at this point we've established the invariants that open() checks for
-        let mut list = AcidList::open(file)?;
+        let mut list = AcidList::open(backing)?;
 
-        // initialize each list head to point to itself, making it empty
-        for head_idx in 0..header.heads {
+        let heads = header.heads();
+        let nodes = header.nodes();
+
+        // initialize each list head to point to itself, making it
+        // empty, including the hidden free list head one past the
+        // heads the caller declared
+        for head_idx in 0..=heads {
             let head_idx = LinkIndex::Head(head_idx).to_node();
             *list.link_mut(head_idx) = Link {
                 previous: head_idx,
@@ -163,8 +297,8 @@ impl<T> AcidList<T> {
         }
 
         // put all the initially-allocated nodes in list 0
-        if header.nodes > 0 {
-            for node_idx in 0..header.nodes {
+        if nodes > 0 {
+            for node_idx in 0..nodes {
                 *list.link_mut(node_idx) = Link {
                     previous: node_idx.wrapping_sub(1),
                     next: node_idx.wrapping_add(1),
@@ -173,9 +307,9 @@ impl<T> AcidList<T> {
 
             let head_idx = LinkIndex::Head(0).to_node();
             list.link_mut(0).previous = head_idx;
-            list.link_mut(header.nodes - 1).next = head_idx;
+            list.link_mut(nodes - 1).next = head_idx;
             *list.link_mut(head_idx) = Link {
-                previous: header.nodes - 1,
+                previous: nodes - 1,
                 next: 0,
             };
         }
@@ -183,92 +317,64 @@ impl<T> AcidList<T> {
         Ok(list)
     }
 
-    pub fn open(mut file: fs::File) -> io::Result<Self> {
-        let fd = file.as_raw_fd();
-        syscall::flock(fd, libc::LOCK_EX)?;
-        let len = file.seek(io::SeekFrom::End(0))?;
-        if len > libc::size_t::max_value() as u64 {
-            return Err(io::Error::new(
-                io::ErrorKind::InvalidData,
-                Error::WrongArchitecture,
-            ));
-        }
-
-        let len = len as libc::size_t;
+    pub fn open(backing: B) -> Result<Self, Error<H::Error>> {
+        let len = backing.len();
 
-        // ensure that list.header() can be called without SIGBUS
-        let expected_size = mem::size_of::<Header<T>>();
+        // ensure that list.header() can be called without going out of bounds
+        let expected_size = mem::size_of::<H>();
         if len < expected_size {
-            return Err(io::Error::new(
-                io::ErrorKind::UnexpectedEof,
-                Error::NotInitialized,
-            ));
+            return Err(Error::NotInitialized);
         }
 
-        let list = AcidList {
-            base: unsafe {
-                syscall::mmap(
-                    std::ptr::null_mut(),
-                    len,
-                    libc::PROT_READ | libc::PROT_WRITE,
-                    libc::MAP_SHARED,
-                    fd,
-                    0,
-                )?
-            },
-            len: len,
+        let mut list: Self = AcidList {
+            backing,
             marker: PhantomData,
         };
 
         let header = list.header();
-        if header.magic != HEADER_MAGIC {
-            return Err(io::Error::new(
-                io::ErrorKind::InvalidData,
-                Error::WrongArchitecture,
-            ));
-        }
+        header.validate().map_err(Error::InvalidHeader)?;
 
-        if header.data_size as usize != mem::size_of::<T>() {
-            return Err(io::Error::new(
-                io::ErrorKind::InvalidData,
-                Error::WrongDataType,
-            ));
+        if header.data_size() as usize != mem::size_of::<T>() {
+            return Err(Error::WrongDataType);
         }
 
         let expected_size = header.file_size();
-        if header.heads < 1
-            || expected_size > usize::max_value() as u64
-            || len != expected_size as usize
+        if header.heads() < 1 || expected_size > usize::max_value() as u64 || len as u64 != expected_size
         {
-            return Err(io::Error::new(
-                io::ErrorKind::InvalidData,
-                Error::NotInitialized,
-            ));
+            return Err(Error::NotInitialized);
         }
 
+        list.replay_journal();
+
         Ok(list)
     }
 
-    pub fn checkpoint(&self) -> io::Result<()> {
-        unsafe { syscall::msync(self.base, self.len, libc::MS_SYNC) }
+    pub fn checkpoint(&self) -> Result<(), B::Error> {
+        self.backing.sync()
     }
 
-    pub fn close(self) -> io::Result<()> {
-        unsafe {
-            syscall::munmap(self.base, self.len)?;
-        }
-
-        // don't let the last-chance Drop implementation run if the
-        // caller explicitly calls close()
-        mem::forget(self);
-        Ok(())
+    /// Like [`checkpoint`](AcidList::checkpoint), but also clears out
+    /// the journal first, so there's nothing left to replay if the
+    /// process is interrupted right afterward.
+    pub fn checkpoint_journal(&mut self) -> Result<(), B::Error> {
+        self.journal_mut().commit = 0;
+        self.backing.sync()
     }
 
-    pub fn header(&self) -> &Header<T> {
-        let header = self.base as *const Header<T>;
+    /// Drop the list, releasing anything its backing holds (e.g. an
+    /// `mmap`ing).
+    pub fn close(self) {}
+
+    pub fn header(&self) -> &H {
+        let header = self.backing.base() as *const H;
         unsafe { &*header }
     }
 
+    pub(crate) fn header_mut(&mut self) -> &mut H {
+        let header = self.backing.base() as *mut H;
+        unsafe { &mut *header }
+    }
+
     pub fn set(&mut self, idx: NodeIndex, value: T) {
         self.node_mut(idx).contents = value;
     }
@@ -285,49 +391,70 @@ impl<T> AcidList<T> {
         }
     }
 
-    pub fn move_before(&mut self, from_idx: NodeIndex, to_next_idx: LinkIndex) {
+    pub fn cursor(&self, head_idx: NodeIndex) -> Cursor<'_, T, B, H> {
+        Cursor::head(self, head_idx)
+    }
+
+    pub fn cursor_mut(&mut self, head_idx: NodeIndex) -> CursorMut<'_, T, B, H> {
+        CursorMut::head(self, head_idx)
+    }
+
+    pub fn iter(&self, head_idx: NodeIndex) -> ListIter<'_, T, B, H> {
+        ListIter::new(self, head_idx)
+    }
+
+    pub fn move_before(&mut self, from_idx: NodeIndex, to_next_idx: LinkIndex) -> Result<(), B::Error> {
         assert!(LinkIndex::Node(from_idx) != to_next_idx);
 
         let from = self.link(LinkIndex::Node(from_idx));
 
         if from.next == to_next_idx.to_node() {
             // node is already in the requested spot
-            return;
+            return Ok(());
         }
 
         self.move_to(from_idx, from, Link {
             previous: self.link(to_next_idx).previous,
             next: to_next_idx.to_node(),
-        });
+        })
     }
 
-    pub fn move_after(&mut self, from_idx: NodeIndex, to_previous_idx: LinkIndex) {
+    pub fn move_after(&mut self, from_idx: NodeIndex, to_previous_idx: LinkIndex) -> Result<(), B::Error> {
         assert!(LinkIndex::Node(from_idx) != to_previous_idx);
 
         let from = self.link(LinkIndex::Node(from_idx));
 
         if from.previous == to_previous_idx.to_node() {
             // node is already in the requested spot
-            return;
+            return Ok(());
         }
 
         self.move_to(from_idx, from, Link {
             previous: to_previous_idx.to_node(),
             next: self.link(to_previous_idx).next,
-        });
+        })
     }
 
-    fn move_to(&mut self, from_idx: NodeIndex, from: Link, to: Link) {
-        self.link_mut(from.next).previous = from.previous;
-        self.link_mut(from.previous).next = from.next;
-        *self.link_mut(from_idx) = to;
-        self.link_mut(to.next).previous = from_idx;
-        self.link_mut(to.previous).next = from_idx;
+    /// Reposition `from_idx` so its links become `to`, crash-atomically:
+    /// the writes involved are staged in the journal and applied from
+    /// there, so a reorder interrupted partway through either lands
+    /// completely or gets finished by the next `open`.
+    fn move_to(&mut self, from_idx: NodeIndex, from: Link, to: Link) -> Result<(), B::Error> {
+        let mut patch = LinkPatch::new();
+        patch.entry(self, from.next).previous = from.previous;
+        patch.entry(self, from.previous).next = from.next;
+        *patch.entry(self, from_idx) = to;
+        patch.entry(self, to.next).previous = from_idx;
+        patch.entry(self, to.previous).next = from_idx;
+
+        self.apply_patch(&patch)
     }
 
     unsafe fn head_ptr(&self, idx: NodeIndex) -> *mut Link {
-        assert!(idx < self.header().heads);
-        let base = self.base as *mut u8;
+        // `<=`, not `<`: one head past the ones the caller declared is
+        // reserved for the free list
+        assert!(idx <= self.header().heads());
+        let base = self.backing.base();
         let heads = base.offset(self.header().heads_offset() as isize) as *mut Link;
         heads.offset(idx as isize)
     }
@@ -341,8 +468,8 @@ impl<T> AcidList<T> {
     }
 
     unsafe fn node_ptr(&self, idx: NodeIndex) -> *mut Node<T> {
-        assert!(idx < self.header().nodes);
-        let base = self.base as *mut u8;
+        assert!(idx < self.header().nodes());
+        let base = self.backing.base();
         let nodes = base.offset(self.header().nodes_offset() as isize) as *mut Node<T>;
         nodes.offset(idx as isize)
     }
@@ -370,10 +497,19 @@ impl<T> AcidList<T> {
     }
 }
 
-impl<T> Drop for AcidList<T> {
-    fn drop(&mut self) {
-        unsafe {
-            syscall::munmap(self.base, self.len).expect("AcidList::close");
-        }
+/// Convenience constructors for the common case of a file-backed list.
+#[cfg(feature = "std")]
+impl<T, H: ListHeader<T>> AcidList<T, MmapBacking, H>
+where
+    H::Error: std::error::Error + Send + Sync + 'static,
+{
+    pub fn create_file<P: AsRef<Path>>(path: P, header: H) -> io::Result<Self> {
+        let backing = MmapBacking::create(path, header.file_size())?;
+        AcidList::create(backing, header).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    pub fn open_file(file: fs::File) -> io::Result<Self> {
+        let backing = MmapBacking::open(file)?;
+        AcidList::open(backing).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
     }
 }
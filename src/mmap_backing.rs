@@ -0,0 +1,108 @@
+//! [`Backing`] implementation that memory-maps an on-disk file,
+//! guarded by an exclusive `flock`.
+
+use crate::syscall;
+use crate::{Backing, Growable};
+use std::fs;
+use std::io;
+use std::io::Seek;
+use std::os::unix::io::AsRawFd;
+use std::path::Path;
+
+/// A [`Backing`] that stores list contents in a file and maps it into
+/// memory with `mmap`, so reads and writes go straight through to the
+/// page cache. This is the backing `AcidList` has always used.
+pub struct MmapBacking {
+    file: fs::File,
+    base: *mut libc::c_void,
+    len: libc::size_t,
+}
+
+impl MmapBacking {
+    /// Create a new backing file, sized to hold exactly `file_size`
+    /// bytes, and map it.
+    pub fn create<P: AsRef<Path>>(path: P, file_size: u64) -> io::Result<Self> {
+        let file = fs::OpenOptions::new()
+            .create_new(true)
+            .read(true)
+            .write(true)
+            .open(path)?;
+        file.set_len(file_size)?;
+        Self::open(file)
+    }
+
+    /// Map an already-open file, locking it for exclusive access.
+    pub fn open(mut file: fs::File) -> io::Result<Self> {
+        let fd = file.as_raw_fd();
+        syscall::flock(fd, libc::LOCK_EX)?;
+        let len = file.seek(io::SeekFrom::End(0))?;
+        if len > libc::size_t::max_value() as u64 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "can't open file from a different architecture",
+            ));
+        }
+
+        let len = len as libc::size_t;
+        let base = unsafe {
+            syscall::mmap(
+                std::ptr::null_mut(),
+                len,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_SHARED,
+                fd,
+                0,
+            )?
+        };
+
+        Ok(MmapBacking { file, base, len })
+    }
+}
+
+impl Backing for MmapBacking {
+    type Error = io::Error;
+
+    fn base(&self) -> *mut u8 {
+        self.base as *mut u8
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn sync(&self) -> io::Result<()> {
+        unsafe { syscall::msync(self.base, self.len, libc::MS_SYNC) }
+    }
+}
+
+impl Growable for MmapBacking {
+    fn resize(&mut self, new_len: usize) -> io::Result<()> {
+        self.file.set_len(new_len as u64)?;
+
+        unsafe {
+            syscall::munmap(self.base, self.len)?;
+        }
+
+        self.base = unsafe {
+            syscall::mmap(
+                std::ptr::null_mut(),
+                new_len,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_SHARED,
+                self.file.as_raw_fd(),
+                0,
+            )?
+        };
+        self.len = new_len;
+
+        Ok(())
+    }
+}
+
+impl Drop for MmapBacking {
+    fn drop(&mut self) {
+        unsafe {
+            syscall::munmap(self.base, self.len).expect("MmapBacking::drop");
+        }
+    }
+}
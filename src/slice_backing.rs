@@ -0,0 +1,36 @@
+//! [`Backing`] implementation over a plain in-memory byte slice, for
+//! `no_std` targets and for tests that don't want to touch a
+//! filesystem.
+
+use crate::Backing;
+use core::convert::Infallible;
+
+/// A [`Backing`] over caller-owned memory: a `&mut [u8]` directly, or a
+/// `Vec<u8>` passed by mutable reference (it derefs to a slice).
+/// There's nothing to flush, since the bytes already live wherever the
+/// caller keeps them.
+pub struct SliceBacking<'a> {
+    slice: &'a mut [u8],
+}
+
+impl<'a> SliceBacking<'a> {
+    pub fn new(slice: &'a mut [u8]) -> Self {
+        SliceBacking { slice }
+    }
+}
+
+impl<'a> Backing for SliceBacking<'a> {
+    type Error = Infallible;
+
+    fn base(&self) -> *mut u8 {
+        self.slice.as_ptr() as *mut u8
+    }
+
+    fn len(&self) -> usize {
+        self.slice.len()
+    }
+
+    fn sync(&self) -> Result<(), Infallible> {
+        Ok(())
+    }
+}